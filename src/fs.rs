@@ -0,0 +1,340 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// The primitive filesystem operations `Functions` builds on. Abstracting
+/// over this lets higher-level logic (path confinement, `modify_file`'s
+/// editing, `move_file`'s overwrite checks, old-content return values) be
+/// unit-tested against `FakeFs` instead of requiring a real tempdir and git
+/// repository for every test.
+///
+/// Paths passed to `Fs` methods are always already-resolved, absolute
+/// filesystem paths - confinement and relativization happen in `Functions`
+/// before an `Fs` call is ever made.
+#[async_trait]
+pub trait Fs: Send + Sync {
+    /// Reads the contents of `path`, or `None` if it doesn't exist.
+    async fn read(&self, path: &Path) -> Result<Option<String>>;
+
+    /// Writes `content` to `path`, creating parent directories as needed,
+    /// and returns the file's previous contents (or `None` if it didn't
+    /// exist). Implementations should make the write atomic where possible.
+    async fn write(&self, path: &Path, content: &str) -> Result<Option<String>>;
+
+    /// Removes `path`, erroring with `io::ErrorKind::NotFound` if it
+    /// doesn't exist.
+    async fn remove(&self, path: &Path) -> Result<()>;
+
+    /// Renames `from` to `to`, creating `to`'s parent directories as
+    /// needed.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// Reports whether `path` currently exists.
+    async fn exists(&self, path: &Path) -> Result<bool>;
+
+    /// Lists every file under `root`, recursively, as paths relative to
+    /// `root`.
+    async fn list(&self, root: &Path) -> Result<Vec<String>>;
+
+    /// Copies `from` to `to`, creating `to`'s parent directories as needed,
+    /// and returns `to`'s previous contents (or `None` if it didn't exist).
+    /// Errors with `io::ErrorKind::NotFound` if `from` doesn't exist.
+    /// The default implementation is just `read` + `write`, which is enough
+    /// for every current `Fs` backend.
+    async fn copy(&self, from: &Path, to: &Path) -> Result<Option<String>> {
+        let content = self
+            .read(from)
+            .await?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Source file does not exist"))?;
+        self.write(to, &content).await
+    }
+}
+
+/// The real, disk-backed `Fs` implementation used outside of tests.
+pub struct LocalFs;
+
+#[async_trait]
+impl Fs for LocalFs {
+    async fn read(&self, path: &Path) -> Result<Option<String>> {
+        if fs::metadata(path).await.is_err() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read_to_string(path).await?))
+    }
+
+    async fn write(&self, path: &Path, content: &str) -> Result<Option<String>> {
+        let dir_path = path.parent().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "File path has no parent directory",
+            )
+        })?;
+        fs::create_dir_all(dir_path).await?;
+
+        let old_content = self.read(path).await?;
+
+        // Write to a sibling temp file and rename it into place, so a
+        // panic or crash mid-write can never leave a half-written file at
+        // `path` - readers only ever observe the old contents or the new
+        // ones.
+        let tmp_path = dir_path.join(format!(
+            ".{}.tmp.{}",
+            path.file_name()
+                .ok_or_else(|| io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "File path has no file name"
+                ))?
+                .to_string_lossy(),
+            rand::thread_rng().gen::<u64>()
+        ));
+
+        let mut tmp_file = fs::File::create(&tmp_path).await?;
+        tmp_file.write_all(content.as_bytes()).await?;
+        tmp_file.flush().await?;
+        tmp_file.sync_all().await?;
+        drop(tmp_file);
+
+        fs::rename(&tmp_path, path).await?;
+
+        Ok(old_content)
+    }
+
+    async fn remove(&self, path: &Path) -> Result<()> {
+        if fs::metadata(path).await.is_err() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "File does not exist").into());
+        }
+        fs::remove_file(path).await?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        if let Some(dir_path) = to.parent() {
+            fs::create_dir_all(dir_path).await?;
+        }
+        match fs::rename(from, to).await {
+            Ok(()) => Ok(()),
+            // EXDEV: `from` and `to` live on different filesystems, so a
+            // plain rename isn't possible - fall back to copy + remove.
+            Err(e) if e.raw_os_error() == Some(18) => {
+                fs::copy(from, to).await?;
+                fs::remove_file(from).await?;
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        Ok(fs::metadata(path).await.is_ok())
+    }
+
+    async fn list(&self, root: &Path) -> Result<Vec<String>> {
+        fn walk(dir: &Path, root: &Path, out: &mut Vec<String>) -> Result<()> {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if path.is_dir() {
+                    walk(&path, root, out)?;
+                } else if let Ok(relative) = path.strip_prefix(root) {
+                    if let Some(relative) = relative.to_str() {
+                        out.push(relative.to_string());
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        let root = root.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut out = Vec::new();
+            walk(&root, &root, &mut out)?;
+            Ok(out)
+        })
+        .await?
+    }
+}
+
+/// An in-memory `Fs` backed by a `BTreeMap`, for deterministic unit tests
+/// that shouldn't touch disk.
+#[cfg(test)]
+pub struct FakeFs {
+    files: Mutex<BTreeMap<PathBuf, String>>,
+}
+
+#[cfg(test)]
+impl FakeFs {
+    pub fn new() -> Self {
+        Self {
+            files: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Default for FakeFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Fs for FakeFs {
+    async fn read(&self, path: &Path) -> Result<Option<String>> {
+        Ok(self.files.lock().unwrap().get(path).cloned())
+    }
+
+    async fn write(&self, path: &Path, content: &str) -> Result<Option<String>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), content.to_string()))
+    }
+
+    async fn remove(&self, path: &Path) -> Result<()> {
+        match self.files.lock().unwrap().remove(path) {
+            Some(_) => Ok(()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "File does not exist").into()),
+        }
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let content = files
+            .remove(from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File does not exist"))?;
+        files.insert(to.to_path_buf(), content);
+        Ok(())
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        // A directory exists if any file lives under it, as on disk.
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .keys()
+            .any(|file| file.starts_with(path)))
+    }
+
+    async fn list(&self, root: &Path) -> Result<Vec<String>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .keys()
+            .filter_map(|path| path.strip_prefix(root).ok())
+            .filter_map(|path| path.to_str().map(String::from))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_local_fs_write_read_remove_roundtrip() {
+        let dir = tempdir().expect("Failed to create a temporary directory");
+        let file_path = dir.path().join("nested/test.txt");
+        let local_fs = LocalFs;
+
+        let old = local_fs
+            .write(&file_path, "hello")
+            .await
+            .expect("write should succeed");
+        assert_eq!(old, None);
+
+        assert_eq!(
+            local_fs.read(&file_path).await.unwrap(),
+            Some("hello".to_string())
+        );
+
+        let old = local_fs.write(&file_path, "world").await.unwrap();
+        assert_eq!(old, Some("hello".to_string()));
+
+        local_fs.remove(&file_path).await.unwrap();
+        assert_eq!(local_fs.read(&file_path).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_fake_fs_write_read_remove_roundtrip() {
+        let fake_fs = FakeFs::new();
+        let path = PathBuf::from("/repo/test.txt");
+
+        assert_eq!(fake_fs.write(&path, "hello").await.unwrap(), None);
+        assert_eq!(
+            fake_fs.read(&path).await.unwrap(),
+            Some("hello".to_string())
+        );
+        assert!(fake_fs.exists(&path).await.unwrap());
+
+        fake_fs.remove(&path).await.unwrap();
+        assert!(!fake_fs.exists(&path).await.unwrap());
+        assert!(fake_fs.remove(&path).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fake_fs_rename() {
+        let fake_fs = FakeFs::new();
+        let from = PathBuf::from("/repo/old.txt");
+        let to = PathBuf::from("/repo/new.txt");
+
+        fake_fs.write(&from, "content").await.unwrap();
+        fake_fs.rename(&from, &to).await.unwrap();
+
+        assert!(!fake_fs.exists(&from).await.unwrap());
+        assert_eq!(
+            fake_fs.read(&to).await.unwrap(),
+            Some("content".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_fs_copy_leaves_source_in_place() {
+        let dir = tempdir().expect("Failed to create a temporary directory");
+        let from = dir.path().join("from.txt");
+        let to = dir.path().join("nested/to.txt");
+        let local_fs = LocalFs;
+
+        local_fs.write(&from, "content").await.unwrap();
+        let old = local_fs.copy(&from, &to).await.unwrap();
+        assert_eq!(old, None);
+
+        assert_eq!(
+            local_fs.read(&from).await.unwrap(),
+            Some("content".to_string())
+        );
+        assert_eq!(
+            local_fs.read(&to).await.unwrap(),
+            Some("content".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_fs_copy_leaves_source_in_place() {
+        let fake_fs = FakeFs::new();
+        let from = PathBuf::from("/repo/from.txt");
+        let to = PathBuf::from("/repo/to.txt");
+
+        fake_fs.write(&from, "content").await.unwrap();
+        fake_fs.copy(&from, &to).await.unwrap();
+
+        assert_eq!(
+            fake_fs.read(&from).await.unwrap(),
+            Some("content".to_string())
+        );
+        assert_eq!(
+            fake_fs.read(&to).await.unwrap(),
+            Some("content".to_string())
+        );
+    }
+}
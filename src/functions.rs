@@ -1,12 +1,20 @@
+use crate::fs::{Fs, LocalFs};
 use anyhow::{anyhow, Result};
 use git2::{Repository, StatusOptions};
+use notify::{EventKind, RecursiveMode, Watcher};
+use notify_debouncer_full::new_debouncer;
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io::Write;
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use similar::{ChangeTag, TextDiff};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
 
 pub struct Functions {
-    repo: Repository,
+    repo: Arc<Mutex<Repository>>,
+    fs: Box<dyn Fs>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,6 +32,20 @@ pub struct DeleteFileArgs {
 pub struct MoveFileArgs {
     pub source_path: String,
     pub destination_path: String,
+    /// If `false` (the default), the move is rejected when
+    /// `destination_path` already exists.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CopyFileArgs {
+    pub source_path: String,
+    pub destination_path: String,
+    /// If `false` (the default), the copy is rejected when
+    /// `destination_path` already exists.
+    #[serde(default)]
+    pub overwrite: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -31,63 +53,471 @@ pub struct ReadFileArgs {
     pub path: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModifyFileArgs {
+    pub path: String,
+    pub modification: FileModification,
+    /// The SHA-256 hex digest of the file's contents as last seen by the
+    /// caller (e.g. from `hash_file`). If set and the file's current
+    /// contents hash to something else, the write is aborted with a
+    /// "changed since read" error instead of clobbering a concurrent edit.
+    #[serde(default)]
+    pub expected_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum FileModification {
+    Insert {
+        start_line: usize,
+        content: String,
+    },
+    Replace {
+        start_line: usize,
+        end_line: usize,
+        content: String,
+    },
+    /// Locates `old` as an exact substring of the file and swaps in `new`,
+    /// sidestepping line-number drift entirely. `old` must match exactly
+    /// once unless `occurrence` (1-indexed) picks a specific match among
+    /// several.
+    SearchReplace {
+        old: String,
+        new: String,
+        #[serde(default)]
+        occurrence: Option<usize>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchArgs {
+    pub paths: Vec<String>,
+    pub recursive: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileChangeEvent {
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApplyPatchArgs {
+    pub path: String,
+    /// A standard unified diff (`@@ -a,b +c,d @@` hunk headers followed by
+    /// ` `/`-`/`+` lines) to apply to the file's current contents.
+    pub patch: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StageFilesArgs {
+    /// Paths relative to the repository's root directory.
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommitArgs {
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RestoreVersionArgs {
+    pub path: String,
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IgnorePathArgs {
+    pub path: String,
+}
+
+/// One recorded snapshot of a file, as returned by `list_versions`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    /// Monotonically increasing per-file counter; pass back to
+    /// `restore_version` to roll back to this snapshot.
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModifyFileResult {
+    pub old_contents: String,
+    pub new_contents: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffFileResult {
+    /// The added/removed lines between the HEAD blob and the working copy,
+    /// in the same shape `modify_file` reports its own edits in.
+    pub modifications: Vec<LineModification>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LineModification {
+    pub line: usize,
+    pub content: String,
+    /// Modification is either deletion or insertion. A replacement is therefore
+    /// considered two line modifications.
+    pub is_deletion: bool,
+}
+
+/// The line-ending style of a file, detected by majority vote over its
+/// existing line terminators (ties and empty files default to `Lf`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    fn detect(content: &str) -> Self {
+        let crlf_count = content.matches("\r\n").count();
+        let lf_only_count = content.matches('\n').count().saturating_sub(crlf_count);
+        if crlf_count > lf_only_count {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// The result of a `modify_file` call: the individual line insertions and
+/// deletions applied, plus the line-ending style the file was written back
+/// with, so callers know what they're about to see on next read.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModifyFileOutcome {
+    pub modifications: Vec<LineModification>,
+    pub line_ending: LineEnding,
+}
+
+/// A single line within a parsed patch hunk.
+#[derive(Debug, Clone)]
+enum PatchLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// A parsed `@@ -a,b +c,d @@` hunk: the 1-indexed line in the *old* file
+/// where it claims to start, plus its ordered context/remove/add lines.
+#[derive(Debug, Clone)]
+struct Hunk {
+    header: String,
+    old_start: usize,
+    lines: Vec<PatchLine>,
+}
+
+impl Hunk {
+    /// The context+remove lines, in order, that must match the target file.
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                PatchLine::Context(s) | PatchLine::Remove(s) => Some(s.as_str()),
+                PatchLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    /// The context+add lines, in order, that replace the matched region.
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                PatchLine::Context(s) | PatchLine::Add(s) => Some(s.as_str()),
+                PatchLine::Remove(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Parses a unified diff into its hunks. Only the hunk bodies are
+/// interpreted - file headers (`---`/`+++`) are ignored since we always
+/// apply to a single, already-known file.
+fn parse_patch(patch: &str) -> Result<Vec<Hunk>> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+
+    for line in patch.lines() {
+        if let Some(rest) = line.strip_prefix("@@") {
+            if let Some(hunk) = current.take() {
+                hunks.push(hunk);
+            }
+            let range_spec = rest
+                .split("@@")
+                .next()
+                .ok_or_else(|| anyhow!("Malformed hunk header: `{}`", line))?
+                .trim();
+            let old_range = range_spec
+                .split_whitespace()
+                .find(|tok| tok.starts_with('-'))
+                .ok_or_else(|| anyhow!("Malformed hunk header: `{}`", line))?;
+            let old_start: usize = old_range
+                .trim_start_matches('-')
+                .split(',')
+                .next()
+                .ok_or_else(|| anyhow!("Malformed hunk header: `{}`", line))?
+                .parse()
+                .map_err(|_| anyhow!("Malformed hunk header: `{}`", line))?;
+            current = Some(Hunk {
+                header: line.to_string(),
+                old_start,
+                lines: Vec::new(),
+            });
+        } else if line.starts_with("---") || line.starts_with("+++") {
+            // File header line, not part of any hunk - ignore.
+            continue;
+        } else {
+            let hunk = current
+                .as_mut()
+                .ok_or_else(|| anyhow!("Patch line `{}` appears before any hunk header", line))?;
+            if let Some(content) = line.strip_prefix('-') {
+                hunk.lines.push(PatchLine::Remove(content.to_string()));
+            } else if let Some(content) = line.strip_prefix('+') {
+                hunk.lines.push(PatchLine::Add(content.to_string()));
+            } else {
+                let content = line.strip_prefix(' ').unwrap_or(line);
+                hunk.lines.push(PatchLine::Context(content.to_string()));
+            }
+        }
+    }
+    if let Some(hunk) = current.take() {
+        hunks.push(hunk);
+    }
+
+    Ok(hunks)
+}
+
+/// How many lines on either side of a hunk's stated offset to search for a
+/// matching position when the file has drifted.
+const PATCH_SEARCH_WINDOW: usize = 50;
+
+/// Finds the 0-indexed position in `file_lines` where `hunk`'s context/remove
+/// lines match exactly, starting at `nominal_index` and expanding outward by
+/// up to `PATCH_SEARCH_WINDOW` lines in either direction.
+fn find_hunk_position(file_lines: &[String], hunk: &Hunk, nominal_index: usize) -> Option<usize> {
+    let old_lines = hunk.old_lines();
+    let matches_at = |pos: usize| -> bool {
+        if pos + old_lines.len() > file_lines.len() {
+            return false;
+        }
+        file_lines[pos..pos + old_lines.len()]
+            .iter()
+            .zip(old_lines.iter())
+            .all(|(a, b)| a == b)
+    };
+
+    if matches_at(nominal_index) {
+        return Some(nominal_index);
+    }
+    for offset in 1..=PATCH_SEARCH_WINDOW {
+        if let Some(pos) = nominal_index.checked_sub(offset) {
+            if matches_at(pos) {
+                return Some(pos);
+            }
+        }
+        let pos = nominal_index + offset;
+        if matches_at(pos) {
+            return Some(pos);
+        }
+    }
+    None
+}
+
+/// The 0-indexed, half-open `[start_line, end_line)` span of lines spanned
+/// by the byte range `s..e` within `normalized` (an LF-joined string with no
+/// `\r`). Used to turn a `SearchReplace` match's byte offsets into the same
+/// line-range shape `Insert`/`Replace` already operate on.
+fn line_span(normalized: &str, s: usize, e: usize) -> (usize, usize) {
+    let start_line = normalized[..s].matches('\n').count();
+    let end_line = normalized[..e.saturating_sub(1).max(s)]
+        .matches('\n')
+        .count()
+        + 1;
+    (start_line, end_line)
+}
+
+/// The SHA-256 hex digest of `content`, used to detect whether a file has
+/// changed since it was last read (see `ModifyFileArgs::expected_hash`).
+fn hash_content(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Appends a trailing `\n` to `content` if it's non-empty and doesn't
+/// already end in one, so two texts that only differ in trailing-newline
+/// state line up the same way under a line-oriented diff.
+fn ensure_trailing_newline(mut content: String) -> String {
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content
+}
+
+/// Maps a raw `notify` event kind onto our coarser `FileChangeKind`.
+fn classify_event_kind(kind: &EventKind) -> FileChangeKind {
+    use notify::event::ModifyKind;
+    match kind {
+        EventKind::Create(_) => FileChangeKind::Created,
+        EventKind::Remove(_) => FileChangeKind::Removed,
+        EventKind::Modify(ModifyKind::Name(_)) => FileChangeKind::Renamed,
+        _ => FileChangeKind::Modified,
+    }
+}
+
+/// Gets the filesystem path of a repository, not including /.git/
+fn repo_path_of(repo: &Repository) -> PathBuf {
+    // Here path is path/to/repo/.git/
+    let mut path = repo.path().to_path_buf();
+    path.pop(); // Removes /.git/
+    path
+}
+
+/// Lexically resolves `rel` against an implicit root, without touching the
+/// filesystem: rejects absolute paths and collapses `.`/`..` components,
+/// erroring if a `..` would pop above the root. This is the confinement
+/// check shared by every `Functions` operation that takes an agent-supplied
+/// path, so `../../etc/passwd`-style traversal can't reach outside the repo.
+fn normalize_relative(rel: &str) -> Result<PathBuf> {
+    let rel_path = Path::new(rel);
+    let mut resolved = PathBuf::new();
+    for component in rel_path.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Path `{}` escapes the repository root", rel),
+                    )
+                    .into());
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Path `{}` must be relative to the repository root", rel),
+                )
+                .into());
+            }
+        }
+    }
+    Ok(resolved)
+}
+
 impl Functions {
     pub fn new(repo_path: PathBuf) -> Result<Self> {
+        Self::with_fs(repo_path, Box::new(LocalFs))
+    }
+
+    /// Like `new`, but with an explicit `Fs` backend - lets tests swap in a
+    /// `FakeFs` so path confinement, `modify_file`/`apply_patch`, and
+    /// `move_file`'s overwrite checks can be exercised without touching
+    /// disk.
+    pub fn with_fs(repo_path: PathBuf, fs: Box<dyn Fs>) -> Result<Self> {
         let repo = Repository::open(repo_path)?;
-        Ok(Self { repo })
+        Ok(Self {
+            repo: Arc::new(Mutex::new(repo)),
+            fs,
+        })
     }
 
     /// Gets the filesystem path of the repository, not including /.git/
     fn repo_path(&self) -> PathBuf {
-        // Here path is path/to/repo/.git/
-        let mut path = self.repo.path().to_path_buf();
-        path.pop(); // Removes /.git/
-        path
+        repo_path_of(&self.repo.lock().unwrap())
+    }
+
+    /// Resolves a repo-relative path to an absolute filesystem path, refusing
+    /// to let it escape `repo_path()`. See `normalize_relative` for the
+    /// confinement rules; this additionally re-checks that the joined path
+    /// still starts with the repo root as a defense-in-depth measure.
+    fn resolve_in_repo(&self, rel: &str) -> Result<PathBuf> {
+        let resolved = normalize_relative(rel)?;
+        let repo_path = self.repo_path();
+        let full = repo_path.join(&resolved);
+        if !full.starts_with(&repo_path) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Path `{}` escapes the repository root", rel),
+            )
+            .into());
+        }
+        Ok(full)
     }
 
     /// Returns a list of all commited, staged, and untracked files in the repo.
     /// Does not return any ignored files.
     /// Returns files with a `PathBuf` relative to the repo path. For example:
     /// `[".gitignore", "Cargo.toml", "src/main.rs"]`
-    pub fn list_files(&self) -> Result<Vec<String>> {
-        let mut status_options = StatusOptions::new();
-        status_options.include_ignored(false);
-        status_options.include_untracked(true);
-        status_options.include_unmodified(true);
-        let statuses = self.repo.statuses(Some(&mut status_options))?;
-        let repo_path = self.repo_path();
+    ///
+    /// `git2` is a synchronous library, so the status walk runs on a blocking
+    /// thread via `spawn_blocking` - otherwise it would stall the tokio
+    /// reactor driving the websocket read loop for however long the walk
+    /// takes.
+    pub async fn list_files(&self) -> Result<Vec<String>> {
+        let repo = Arc::clone(&self.repo);
+        tokio::task::spawn_blocking(move || {
+            let repo = repo.lock().unwrap();
+            let mut status_options = StatusOptions::new();
+            status_options.include_ignored(false);
+            status_options.include_untracked(true);
+            status_options.include_unmodified(true);
+            let statuses = repo.statuses(Some(&mut status_options))?;
+            let repo_path = repo_path_of(&repo);
 
-        let mut files = Vec::with_capacity(statuses.len());
-        for entry in statuses.iter() {
-            let Some(file_path) = entry.path() else {
-                print!("Found file path with invalid utf8 name");
-                continue;
-            };
-            let path = PathBuf::from(file_path);
-            if repo_path.join(&path).exists() {
-                files.push(file_path.to_string());
+            let mut files = Vec::with_capacity(statuses.len());
+            for entry in statuses.iter() {
+                let Some(file_path) = entry.path() else {
+                    print!("Found file path with invalid utf8 name");
+                    continue;
+                };
+                let path = PathBuf::from(file_path);
+                if repo_path.join(&path).exists() {
+                    files.push(file_path.to_string());
+                }
             }
-        }
 
-        Ok(files)
+            Ok(files)
+        })
+        .await?
     }
 
     /// Creates a new file and echoes `content` into the file.
     /// `path` is relative to the repo path.
     /// Returns the old file.
-    pub fn write_file(&self, args: WriteFileArgs) -> Result<Option<String>> {
-        let repo_path = self.repo_path();
-        let file_path = repo_path.join(&args.path);
-
-        if let Some(dir_path) = file_path.parent() {
-            fs::create_dir_all(dir_path)?;
-        }
-
-        let old_file = self.read_file(ReadFileArgs { path: args.path })?;
-        let mut file = File::create(file_path)?;
-        file.write_all(args.content.as_bytes())?;
+    ///
+    /// The write is atomic: the `Fs` backend writes `content` to a
+    /// temporary sibling file and renames it into place, so a panic, power
+    /// loss, or full disk mid-write can never leave a half-written or
+    /// truncated file at `path` - readers only ever observe the old
+    /// contents or the new ones.
+    pub async fn write_file(&self, args: WriteFileArgs) -> Result<Option<String>> {
+        self.record_snapshot(&args.path).await?;
+        self.write_file_inner(args).await
+    }
 
-        Ok(old_file)
+    /// The raw write, without recording a snapshot first. Used by callers
+    /// (like `modify_file`) that have already snapshotted the file's prior
+    /// contents themselves, so it isn't recorded twice.
+    async fn write_file_inner(&self, args: WriteFileArgs) -> Result<Option<String>> {
+        let file_path = self.resolve_in_repo(&args.path)?;
+        self.fs.write(&file_path, &args.content).await
     }
 
     /// Reads the contents of a file from the repository.
@@ -101,14 +531,101 @@ impl Functions {
     /// # Returns
     /// A `Result` containing the file contents as a `String`, or
     /// an error if there is a problem reading the file.
-    pub fn read_file(&self, args: ReadFileArgs) -> Result<Option<String>> {
-        let repo_path = self.repo_path();
-        let file_path = repo_path.join(args.path);
-        if !file_path.exists() {
-            return Ok(None);
-        }
-        let file_contents = std::fs::read_to_string(file_path)?;
-        Ok(Some(file_contents))
+    pub async fn read_file(&self, args: ReadFileArgs) -> Result<Option<String>> {
+        let file_path = self.resolve_in_repo(&args.path)?;
+        self.fs.read(&file_path).await
+    }
+
+    /// Cheaply checks whether a file exists in the repository, without
+    /// reading or returning its contents.
+    ///
+    /// `path` is relative to the repository's root directory.
+    pub async fn exists(&self, args: ReadFileArgs) -> Result<bool> {
+        let file_path = self.resolve_in_repo(&args.path)?;
+        self.fs.exists(&file_path).await
+    }
+
+    /// Returns the SHA-256 hex digest of a file's current contents, or
+    /// `None` if it doesn't exist. Callers can capture this at read time
+    /// and thread it back in as `ModifyFileArgs::expected_hash` to get safe
+    /// read-modify-write semantics.
+    pub async fn hash_file(&self, args: ReadFileArgs) -> Result<Option<String>> {
+        Ok(self
+            .read_file(args)
+            .await?
+            .map(|content| hash_content(&content)))
+    }
+
+    /// Loads the contents of `path` as committed at HEAD, without touching
+    /// the working tree.
+    ///
+    /// Returns `None` if HEAD has no commits yet, or if `path` isn't tracked
+    /// in the HEAD tree (e.g. it's a new, uncommitted file). `git2` is
+    /// synchronous, so the tree walk runs via `spawn_blocking`.
+    pub async fn read_head_file(&self, args: ReadFileArgs) -> Result<Option<String>> {
+        let relative_path = normalize_relative(&args.path)?;
+        let repo = Arc::clone(&self.repo);
+        tokio::task::spawn_blocking(move || {
+            let repo = repo.lock().unwrap();
+            let head = match repo.head() {
+                Ok(head) => head,
+                Err(_) => return Ok(None), // unborn HEAD, no commits yet
+            };
+            let tree = head.peel_to_tree()?;
+            let entry = match tree.get_path(&relative_path) {
+                Ok(entry) => entry,
+                Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            let blob = entry.to_object(&repo)?.peel_to_blob()?;
+            let contents = String::from_utf8(blob.content().to_vec())?;
+            Ok(Some(contents))
+        })
+        .await?
+    }
+
+    /// Diffs `path`'s working-tree contents against its HEAD-committed
+    /// contents, so callers can see exactly what they've changed without
+    /// shelling out to `git diff`. Either side may be empty (a new,
+    /// uncommitted file, or one deleted from the working tree).
+    pub async fn diff_file(&self, args: ReadFileArgs) -> Result<DiffFileResult> {
+        let head_contents = self
+            .read_head_file(ReadFileArgs {
+                path: args.path.clone(),
+            })
+            .await?
+            .unwrap_or_default();
+        let working_contents = self.read_file(args).await?.unwrap_or_default();
+
+        // `similar` treats a line's trailing newline as part of its content,
+        // so if HEAD and the working copy only disagree on whether the last
+        // line is newline-terminated, it'd otherwise report that unchanged
+        // line as a spurious delete+insert. Normalizing both sides to always
+        // end in `\n` (when non-empty) keeps the diff to genuine content
+        // changes.
+        let head_contents = ensure_trailing_newline(head_contents);
+        let working_contents = ensure_trailing_newline(working_contents);
+
+        let diff = TextDiff::from_lines(&head_contents, &working_contents);
+        let modifications = diff
+            .iter_all_changes()
+            .filter(|change| change.tag() != ChangeTag::Equal)
+            .map(|change| {
+                let is_deletion = change.tag() == ChangeTag::Delete;
+                let line = if is_deletion {
+                    change.old_index().unwrap() + 1
+                } else {
+                    change.new_index().unwrap() + 1
+                };
+                LineModification {
+                    line,
+                    content: change.value().trim_end_matches('\n').to_string(),
+                    is_deletion,
+                }
+            })
+            .collect();
+
+        Ok(DiffFileResult { modifications })
     }
 
     /// Deletes a file in the repository.
@@ -122,14 +639,10 @@ impl Functions {
     /// # Returns
     /// A `Result` which is an Ok(()) if the file was successfully deleted,
     /// or an error if the file doesn't exist or there is a problem deleting the file.
-    pub fn delete_file(&self, args: DeleteFileArgs) -> Result<()> {
-        let repo_path = self.repo_path();
-        let file_path = repo_path.join(args.path);
-        if !file_path.exists() {
-            return Err(anyhow!("File does not exist"));
-        }
-        std::fs::remove_file(file_path)?;
-        Ok(())
+    pub async fn delete_file(&self, args: DeleteFileArgs) -> Result<()> {
+        self.record_snapshot(&args.path).await?;
+        let file_path = self.resolve_in_repo(&args.path)?;
+        self.fs.remove(&file_path).await
     }
 
     /// Moves a file from one path to another within the repository.
@@ -144,44 +657,826 @@ impl Functions {
     ///
     /// # Returns
     /// A `Result` containing `()`, or an error if the file cannot be moved.
-    pub fn move_file(&self, args: MoveFileArgs) -> Result<()> {
-        let repo_path = self.repo_path();
-        let source_file_path = repo_path.join(&args.source_path);
-        let destination_file_path = repo_path.join(&args.destination_path);
+    pub async fn move_file(&self, args: MoveFileArgs) -> Result<()> {
+        let source_file_path = self.resolve_in_repo(&args.source_path)?;
+        let destination_file_path = self.resolve_in_repo(&args.destination_path)?;
 
         // Ensure the source file exists
-        if !source_file_path.exists() {
-            return Err(anyhow!("Source file does not exist"));
+        if !self.fs.exists(&source_file_path).await? {
+            return Err(
+                io::Error::new(io::ErrorKind::NotFound, "Source file does not exist").into(),
+            );
+        }
+
+        if !args.overwrite && self.fs.exists(&destination_file_path).await? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "Destination file already exists",
+            )
+            .into());
         }
 
-        // Ensure the destination doesn't already exist
-        if destination_file_path.exists() {
-            return Err(anyhow!("Destination file already exists"));
+        self.fs
+            .rename(&source_file_path, &destination_file_path)
+            .await
+    }
+
+    /// Copies a file from one path to another within the repository,
+    /// leaving the source in place.
+    ///
+    /// Both paths are relative to the repository's root directory. Fails if
+    /// the source doesn't exist, or if the destination already exists and
+    /// `overwrite` is `false`.
+    pub async fn copy_file(&self, args: CopyFileArgs) -> Result<()> {
+        let source_file_path = self.resolve_in_repo(&args.source_path)?;
+        let destination_file_path = self.resolve_in_repo(&args.destination_path)?;
+
+        if !self.fs.exists(&source_file_path).await? {
+            return Err(
+                io::Error::new(io::ErrorKind::NotFound, "Source file does not exist").into(),
+            );
         }
 
-        if let Some(dir_path) = destination_file_path.parent() {
-            fs::create_dir_all(dir_path)?;
+        if !args.overwrite && self.fs.exists(&destination_file_path).await? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "Destination file already exists",
+            )
+            .into());
         }
-        fs::rename(source_file_path, destination_file_path)?;
+
+        self.fs
+            .copy(&source_file_path, &destination_file_path)
+            .await?;
         Ok(())
     }
+
+    /// Applies a `FileModification` to a file: an `Insert` or `Replace` at
+    /// the given 1-indexed line range, or a `SearchReplace` of an exact
+    /// substring.
+    ///
+    /// `path` is relative to the repository's root directory. The file must
+    /// already exist. The file's original line-ending style (LF or CRLF) and
+    /// trailing-newline state are detected and preserved in the write-back,
+    /// so editing a CRLF or no-trailing-newline file doesn't rewrite its
+    /// line endings as an unrelated side effect.
+    pub async fn modify_file(&self, args: ModifyFileArgs) -> Result<ModifyFileOutcome> {
+        let file = self
+            .read_file(ReadFileArgs {
+                path: args.path.clone(),
+            })
+            .await?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File does not exist"))?;
+
+        if let Some(expected_hash) = &args.expected_hash {
+            let actual_hash = hash_content(&file);
+            if &actual_hash != expected_hash {
+                return Err(anyhow!(
+                    "File `{}` changed since it was read: expected hash {}, found {}",
+                    args.path,
+                    expected_hash,
+                    actual_hash
+                ));
+            }
+        }
+
+        self.record_snapshot_content(&args.path, &file).await?;
+
+        let line_ending = LineEnding::detect(&file);
+        let had_trailing_newline = file.ends_with('\n');
+        let mut file_content = file.lines().map(String::from).collect::<Vec<_>>();
+        let mut modifications = Vec::new();
+
+        match args.modification {
+            FileModification::Insert {
+                start_line,
+                content,
+            } => {
+                let insert_index = start_line.saturating_sub(1); // Convert 1-indexed to 0-indexed
+                for (i, line_content) in content.split('\n').enumerate() {
+                    file_content.insert(insert_index + i, line_content.to_string());
+                    modifications.push(LineModification {
+                        line: insert_index + i + 1, // Convert back to 1-indexed
+                        content: line_content.into(),
+                        is_deletion: false,
+                    })
+                }
+            }
+            FileModification::Replace {
+                start_line,
+                end_line,
+                content,
+            } => {
+                let replace_start = start_line.saturating_sub(1); // Convert 1-indexed to 0-indexed
+                let replace_end = end_line.saturating_sub(1);
+
+                // Record deletions
+                for i in replace_start..replace_end {
+                    if let Some(original_content) = file_content.get(i) {
+                        modifications.push(LineModification {
+                            line: i + 1, // Convert back to 1-indexed
+                            content: original_content.to_string(),
+                            is_deletion: true,
+                        });
+                    }
+                }
+
+                // Replace content
+                file_content.splice(
+                    replace_start..replace_end,
+                    content.split('\n').map(String::from),
+                );
+
+                // Record insertions
+                for (i, line_content) in content.split('\n').enumerate() {
+                    modifications.push(LineModification {
+                        line: replace_start + i + 1, // Convert back to 1-indexed
+                        content: line_content.into(),
+                        is_deletion: false,
+                    });
+                }
+            }
+            FileModification::SearchReplace {
+                old,
+                new,
+                occurrence,
+            } => {
+                if old.is_empty() {
+                    return Err(anyhow!("`old` must not be empty"));
+                }
+
+                // `file_content` (and therefore `normalized` below) comes
+                // from `str::lines()`, which strips `\r` along with the
+                // `\n` it splits on - so it never contains a literal `\r`.
+                // `old`/`new` may, if the caller captured them verbatim
+                // from a CRLF file's contents (e.g. via `read_file`), so
+                // strip it here too or a perfectly-matching `old` would
+                // never be found.
+                let old = old.replace("\r\n", "\n");
+                let new = new.replace("\r\n", "\n");
+
+                let normalized = file_content.join("\n");
+                let match_starts: Vec<usize> = normalized
+                    .match_indices(old.as_str())
+                    .map(|(i, _)| i)
+                    .collect();
+
+                let start = match occurrence {
+                    Some(n) => *match_starts.get(n.wrapping_sub(1)).ok_or_else(|| {
+                        anyhow!(
+                            "`occurrence` {} out of range: `old` matched {} time(s) in `{}`",
+                            n,
+                            match_starts.len(),
+                            args.path
+                        )
+                    })?,
+                    None => match match_starts.as_slice() {
+                        [single] => *single,
+                        matches => {
+                            return Err(anyhow!(
+                                "`old` must match exactly once in `{}` (matched {} time(s)); \
+                                 pass `occurrence` to disambiguate",
+                                args.path,
+                                matches.len()
+                            ))
+                        }
+                    },
+                };
+                let end = start + old.len();
+
+                let new_normalized =
+                    format!("{}{}{}", &normalized[..start], new, &normalized[end..]);
+
+                let (replace_start, replace_end) = line_span(&normalized, start, end);
+                let (new_start, new_end) = line_span(&new_normalized, start, start + new.len());
+                debug_assert_eq!(replace_start, new_start);
+
+                let new_lines: Vec<String> = new_normalized.split('\n').map(String::from).collect();
+
+                // Record deletions
+                for i in replace_start..replace_end {
+                    if let Some(original_content) = file_content.get(i) {
+                        modifications.push(LineModification {
+                            line: i + 1, // Convert back to 1-indexed
+                            content: original_content.to_string(),
+                            is_deletion: true,
+                        });
+                    }
+                }
+
+                // Record insertions
+                for (i, line_content) in new_lines[new_start..new_end].iter().enumerate() {
+                    modifications.push(LineModification {
+                        line: replace_start + i + 1, // Convert back to 1-indexed
+                        content: line_content.clone(),
+                        is_deletion: false,
+                    });
+                }
+
+                file_content.splice(
+                    replace_start..replace_end,
+                    new_lines[new_start..new_end].iter().cloned(),
+                );
+            }
+        }
+
+        let mut new_contents = file_content.join(line_ending.as_str());
+        if had_trailing_newline {
+            new_contents.push_str(line_ending.as_str());
+        }
+
+        self.write_file_inner(WriteFileArgs {
+            path: args.path,
+            content: new_contents,
+        })
+        .await?;
+
+        Ok(ModifyFileOutcome {
+            modifications,
+            line_ending,
+        })
+    }
+
+    /// Applies a unified diff to a file, tolerating small drift in the
+    /// hunks' stated line numbers.
+    ///
+    /// Each hunk's context and `-` lines are matched against the file
+    /// starting at the hunk's stated offset; if they don't match there, a
+    /// window of `PATCH_SEARCH_WINDOW` lines on either side is searched for
+    /// an exact match. If no hunk can be matched anywhere in its window, the
+    /// whole patch is rejected - no partial writes - with an error naming
+    /// the failing hunk.
+    ///
+    /// As with `modify_file`, the file's original line-ending style (LF or
+    /// CRLF) and trailing-newline state are detected and preserved in the
+    /// write-back.
+    pub async fn apply_patch(&self, args: ApplyPatchArgs) -> Result<ModifyFileResult> {
+        let old_contents = self
+            .read_file(ReadFileArgs {
+                path: args.path.clone(),
+            })
+            .await?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File does not exist"))?;
+
+        let line_ending = LineEnding::detect(&old_contents);
+        let had_trailing_newline = old_contents.ends_with('\n');
+        let mut file_lines: Vec<String> = old_contents.lines().map(String::from).collect();
+        let hunks = parse_patch(&args.patch)?;
+
+        let mut offset: isize = 0;
+        for hunk in &hunks {
+            let nominal_index = ((hunk.old_start as isize - 1) + offset).max(0) as usize;
+            let pos = find_hunk_position(&file_lines, hunk, nominal_index).ok_or_else(|| {
+                anyhow!(
+                    "Conflict applying patch to `{}`: hunk `{}` did not match the file \
+                     within {} lines of line {}",
+                    args.path,
+                    hunk.header,
+                    PATCH_SEARCH_WINDOW,
+                    hunk.old_start,
+                )
+            })?;
+
+            let old_lines = hunk.old_lines();
+            let new_lines = hunk.new_lines();
+            file_lines.splice(
+                pos..pos + old_lines.len(),
+                new_lines.iter().map(|s| s.to_string()),
+            );
+
+            offset += (pos as isize - nominal_index as isize)
+                + (new_lines.len() as isize - old_lines.len() as isize);
+        }
+
+        let mut new_contents = file_lines.join(line_ending.as_str());
+        if had_trailing_newline {
+            new_contents.push_str(line_ending.as_str());
+        }
+        self.write_file(WriteFileArgs {
+            path: args.path,
+            content: new_contents.clone(),
+        })
+        .await?;
+
+        Ok(ModifyFileResult {
+            old_contents,
+            new_contents,
+        })
+    }
+
+    /// Watches `args.paths` (relative to the repo root) for filesystem
+    /// changes and forwards debounced `FileChangeEvent`s to `sink` as they
+    /// occur. `.git/` and gitignored paths are filtered out, matching
+    /// `list_files`'s ignore behaviour.
+    ///
+    /// The watcher runs on its own background thread (the `notify` backend
+    /// is callback-driven, not async) for as long as `sink`'s receiving end
+    /// stays open; once the receiver is dropped, the next event causes the
+    /// thread to exit.
+    pub fn watch(&self, args: WatchArgs, sink: UnboundedSender<FileChangeEvent>) -> Result<()> {
+        let repo_path = self.repo_path();
+        let mut absolute_paths = Vec::with_capacity(args.paths.len());
+        for path in &args.paths {
+            absolute_paths.push(self.resolve_in_repo(path)?);
+        }
+
+        let repo = Arc::clone(&self.repo);
+        let recursive = args.recursive;
+        std::thread::spawn(move || {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let mut debouncer = match new_debouncer(Duration::from_millis(100), None, tx) {
+                Ok(debouncer) => debouncer,
+                Err(e) => {
+                    eprintln!("Failed to start filesystem watcher: {}", e);
+                    return;
+                }
+            };
+
+            let mode = if recursive {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
+            for path in &absolute_paths {
+                if let Err(e) = debouncer.watcher().watch(path, mode) {
+                    eprintln!("Failed to watch {}: {}", path.display(), e);
+                }
+            }
+
+            for result in rx {
+                let Ok(events) = result else { continue };
+                for event in events {
+                    for changed_path in &event.event.paths {
+                        if changed_path.components().any(|c| c.as_os_str() == ".git") {
+                            continue;
+                        }
+                        let is_ignored = {
+                            let repo = repo.lock().unwrap();
+                            repo.status_should_ignore(changed_path).unwrap_or(false)
+                        };
+                        if is_ignored {
+                            continue;
+                        }
+                        let Ok(relative_path) = changed_path.strip_prefix(&repo_path) else {
+                            continue;
+                        };
+                        let Some(relative_path) = relative_path.to_str() else {
+                            continue;
+                        };
+
+                        let change = FileChangeEvent {
+                            path: relative_path.to_string(),
+                            kind: classify_event_kind(&event.event.kind),
+                        };
+                        if sink.send(change).is_err() {
+                            return; // Receiver dropped - stop watching.
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Stages `paths` (relative to the repo root) into the index, mirroring
+    /// `git add`. Accepts pathspecs, so directories and globs work the same
+    /// way they would on the command line.
+    pub async fn stage_files(&self, args: StageFilesArgs) -> Result<()> {
+        let repo = Arc::clone(&self.repo);
+        tokio::task::spawn_blocking(move || {
+            let repo = repo.lock().unwrap();
+            let mut index = repo.index()?;
+            index.add_all(args.paths.iter(), git2::IndexAddOption::DEFAULT, None)?;
+            index.write()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Unstages `paths`, resetting their index entries back to HEAD's (or
+    /// removing them from the index entirely if HEAD is unborn).
+    pub async fn unstage_files(&self, args: StageFilesArgs) -> Result<()> {
+        let repo = Arc::clone(&self.repo);
+        tokio::task::spawn_blocking(move || {
+            let repo = repo.lock().unwrap();
+            match repo.head() {
+                Ok(head) => {
+                    let head_commit = head.peel_to_commit()?;
+                    repo.reset_default(Some(head_commit.as_object()), args.paths.iter())?;
+                }
+                Err(_) => {
+                    let mut index = repo.index()?;
+                    for path in &args.paths {
+                        index.remove_path(Path::new(path))?;
+                    }
+                    index.write()?;
+                }
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Builds a tree from the current index and creates a commit on HEAD,
+    /// checkpointing whatever has been staged via `stage_files`. Creates the
+    /// repository's first commit if HEAD is unborn. Returns the new commit's
+    /// oid as a hex string.
+    pub async fn commit(&self, args: CommitArgs) -> Result<String> {
+        let repo = Arc::clone(&self.repo);
+        tokio::task::spawn_blocking(move || {
+            let repo = repo.lock().unwrap();
+            let mut index = repo.index()?;
+            let tree_oid = index.write_tree()?;
+            let tree = repo.find_tree(tree_oid)?;
+            let signature = repo
+                .signature()
+                .or_else(|_| git2::Signature::now("jdev-cli", "jdev-cli@localhost"))?;
+            let parent_commit = match repo.head() {
+                Ok(head) => Some(head.peel_to_commit()?),
+                Err(_) => None, // Unborn HEAD: this becomes the initial commit.
+            };
+            let parents = parent_commit.iter().collect::<Vec<_>>();
+            let oid = repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &args.message,
+                &tree,
+                &parents,
+            )?;
+            Ok(oid.to_string())
+        })
+        .await?
+    }
+
+    /// The root directory snapshots are stored under: inside the repo's
+    /// `.git/` directory, so they never show up in `git status` or
+    /// `list_files` without needing a `.gitignore` entry.
+    fn snapshots_root(&self) -> PathBuf {
+        self.repo.lock().unwrap().path().join("jdev/snapshots")
+    }
+
+    /// The directory holding `relative`'s numbered snapshots, mirroring its
+    /// path under `snapshots_root()`.
+    fn snapshot_dir(&self, relative: &Path) -> PathBuf {
+        self.snapshots_root().join(relative)
+    }
+
+    /// Records `content` as the next numbered snapshot of `relative_path`.
+    async fn record_snapshot_content(&self, relative_path: &str, content: &str) -> Result<()> {
+        let relative = normalize_relative(relative_path)?;
+        let dir = self.snapshot_dir(&relative);
+        let existing = if self.fs.exists(&dir).await? {
+            self.fs.list(&dir).await?
+        } else {
+            Vec::new()
+        };
+        let next_version = existing
+            .iter()
+            .filter_map(|name| name.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.fs
+            .write(&dir.join(next_version.to_string()), content)
+            .await?;
+        Ok(())
+    }
+
+    /// Snapshots `relative_path`'s current on-disk contents, if it exists.
+    /// A no-op for paths with no prior content (e.g. a brand new file) -
+    /// there's nothing meaningful to roll back to.
+    async fn record_snapshot(&self, relative_path: &str) -> Result<()> {
+        let file_path = self.resolve_in_repo(relative_path)?;
+        let Some(content) = self.fs.read(&file_path).await? else {
+            return Ok(());
+        };
+        self.record_snapshot_content(relative_path, &content).await
+    }
+
+    /// Lists the snapshots recorded for `path` by `write_file`,
+    /// `modify_file`, and `delete_file`, oldest first.
+    pub async fn list_versions(&self, args: ReadFileArgs) -> Result<Vec<VersionInfo>> {
+        let relative = normalize_relative(&args.path)?;
+        let dir = self.snapshot_dir(&relative);
+        let mut versions = if self.fs.exists(&dir).await? {
+            self.fs
+                .list(&dir)
+                .await?
+                .iter()
+                .filter_map(|name| name.parse::<u64>().ok())
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+        versions.sort_unstable();
+        Ok(versions
+            .into_iter()
+            .map(|version| VersionInfo { version })
+            .collect())
+    }
+
+    /// Rewrites `path`'s working-tree contents from a previously recorded
+    /// snapshot. The prior (about-to-be-overwritten) contents are themselves
+    /// snapshotted first via `write_file`, so a restore can be undone too.
+    pub async fn restore_version(&self, args: RestoreVersionArgs) -> Result<()> {
+        let relative = normalize_relative(&args.path)?;
+        let snapshot_path = self.snapshot_dir(&relative).join(args.version.to_string());
+        let content = self.fs.read(&snapshot_path).await?.ok_or_else(|| {
+            anyhow!(
+                "No snapshot version {} found for `{}`",
+                args.version,
+                args.path
+            )
+        })?;
+        self.write_file(WriteFileArgs {
+            path: args.path,
+            content,
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Appends `path` to the repo root `.gitignore` (creating it if absent),
+    /// so it's subsequently filtered out of `list_files`. Refuses to ignore
+    /// `.gitignore` itself, and is a no-op if an equivalent entry is already
+    /// present. Returns the full set of patterns in the ignore file
+    /// afterwards.
+    pub async fn ignore_path(&self, args: IgnorePathArgs) -> Result<Vec<String>> {
+        let relative = normalize_relative(&args.path)?;
+        let mut entry = relative
+            .to_str()
+            .ok_or_else(|| anyhow!("Path `{}` is not valid UTF-8", args.path))?
+            .replace('\\', "/");
+
+        // `PathBuf` can't represent a bare trailing slash, so
+        // `normalize_relative` always strips it - recover it from the
+        // original string so a directory-only pattern like `build/` isn't
+        // silently widened into one that also matches a file named `build`.
+        if args.path.ends_with('/') && !entry.ends_with('/') {
+            entry.push('/');
+        }
+
+        if entry == ".gitignore" {
+            return Err(anyhow!("Refusing to ignore `.gitignore` itself"));
+        }
+
+        let gitignore_path = self.repo_path().join(".gitignore");
+        let existing = self.fs.read(&gitignore_path).await?.unwrap_or_default();
+        let mut lines = existing.lines().map(String::from).collect::<Vec<_>>();
+
+        if !lines.iter().any(|line| line.trim() == entry) {
+            lines.push(entry);
+            // Rebuilt from scratch (rather than appended to the raw string),
+            // so a missing trailing newline on the existing file can never
+            // concatenate the new entry onto the last line.
+            let new_contents = lines.join("\n") + "\n";
+            self.fs.write(&gitignore_path, &new_contents).await?;
+        }
+
+        Ok(lines)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fs::FakeFs;
+    use std::fs;
     use tempfile::tempdir;
 
-    /// Sets up a temporary repository in a temporary directory for testing purposes.
-    // fn setup_test_repo() -> Result<(Repository, PathBuf)> {
-    //     let temp_dir = tempdir().expect("Failed to create a temporary directory");
-    //     let repo_path = temp_dir.into_path();
-    //     let repo = Repository::init(&repo_path)?;
-    //     Ok((repo, repo_path.into()))
-    // }
+    #[tokio::test]
+    async fn test_modify_file_insert() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\nLine2").expect("Should write to test file");
 
-    #[test]
-    fn test_file_creation_and_deletion() {
+        functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::Insert {
+                    start_line: 2,
+                    content: "Inserted Line".to_string(),
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("File modification should succeed");
+
+        let file_content = fs::read_to_string(file_path).expect("Should read modified file");
+        assert_eq!(
+            file_content, "Line1\nInserted Line\nLine2",
+            "Content should be inserted correctly"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_modify_file_replace() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\nLine2\nLine3").expect("Should write to test file");
+
+        functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::Replace {
+                    start_line: 2,
+                    end_line: 3,
+                    content: "Replaced Line".to_string(),
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("File modification should succeed");
+
+        let file_content = fs::read_to_string(file_path).expect("Should read modified file");
+        assert_eq!(
+            file_content, "Line1\nReplaced Line\nLine3",
+            "Content should be replaced correctly"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_modify_file_preserves_crlf_and_trailing_newline() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\r\nLine2\r\nLine3\r\n").expect("Should write to test file");
+
+        let outcome = functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::Replace {
+                    start_line: 2,
+                    end_line: 3,
+                    content: "Replaced Line".to_string(),
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("File modification should succeed");
+
+        assert_eq!(outcome.line_ending, LineEnding::CrLf);
+        let file_content = fs::read_to_string(file_path).expect("Should read modified file");
+        assert_eq!(
+            file_content, "Line1\r\nReplaced Line\r\nLine3\r\n",
+            "CRLF line endings and the trailing newline should both be preserved"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_modify_file_search_replace() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\nLine2\nLine3").expect("Should write to test file");
+
+        functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::SearchReplace {
+                    old: "Line2".to_string(),
+                    new: "Replaced".to_string(),
+                    occurrence: None,
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("Search-replace should succeed");
+
+        let file_content = fs::read_to_string(&file_path).expect("Should read modified file");
+        assert_eq!(file_content, "Line1\nReplaced\nLine3");
+    }
+
+    #[tokio::test]
+    async fn test_modify_file_search_replace_ambiguous_without_occurrence() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "foo\nfoo\nbar").expect("Should write to test file");
+
+        let err = functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::SearchReplace {
+                    old: "foo".to_string(),
+                    new: "baz".to_string(),
+                    occurrence: None,
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect_err("Ambiguous match should be rejected without an occurrence");
+        assert!(err.to_string().contains("2 time(s)"));
+
+        functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::SearchReplace {
+                    old: "foo".to_string(),
+                    new: "baz".to_string(),
+                    occurrence: Some(2),
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("Specifying the occurrence should disambiguate");
+
+        let file_content = fs::read_to_string(&file_path).expect("Should read modified file");
+        assert_eq!(file_content, "foo\nbaz\nbar");
+    }
+
+    #[tokio::test]
+    async fn test_modify_file_search_replace_matches_crlf_old() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\r\nLine2\r\nLine3\r\n").expect("Should write to test file");
+
+        // `old` captured verbatim from a CRLF file (e.g. via `read_file`)
+        // contains literal `\r\n`, which should still match even though
+        // `modify_file` internally works against `\r`-free lines.
+        functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::SearchReplace {
+                    old: "Line1\r\nLine2".to_string(),
+                    new: "Line1\r\nReplaced".to_string(),
+                    occurrence: None,
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("Search-replace should match despite CRLF line endings in `old`");
+
+        let file_content = fs::read_to_string(&file_path).expect("Should read modified file");
+        assert_eq!(file_content, "Line1\r\nReplaced\r\nLine3\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_modify_file_rejects_stale_expected_hash() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\nLine2").expect("Should write to test file");
+
+        let hash_at_read = functions
+            .hash_file(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .expect("hash_file should succeed")
+            .expect("File should exist");
+
+        // The file changes underneath the caller after it captured the hash.
+        fs::write(&file_path, "Line1\nLine2 changed").expect("Should rewrite test file");
+
+        let err = functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::Insert {
+                    start_line: 1,
+                    content: "Inserted Line".to_string(),
+                },
+                expected_hash: Some(hash_at_read),
+            })
+            .await
+            .expect_err("A stale expected_hash should abort the write");
+        assert!(err.to_string().contains("changed since it was read"));
+
+        let file_content = fs::read_to_string(&file_path).expect("Should read untouched file");
+        assert_eq!(
+            file_content, "Line1\nLine2 changed",
+            "A rejected modify_file must not clobber the concurrent edit"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_creation_and_deletion() {
         let temp_dir = tempdir().expect("Failed to create a temporary directory");
         let repo_path = temp_dir.into_path();
         let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
@@ -203,6 +1498,7 @@ mod tests {
                         .unwrap()
                         .to_string(),
                 })
+                .await
                 .is_ok(),
             "Should be able to create a new file"
         );
@@ -221,6 +1517,7 @@ mod tests {
                         .unwrap()
                         .to_string(),
                 })
+                .await
                 .is_ok(),
             "Should be able to delete the file"
         );
@@ -231,4 +1528,700 @@ mod tests {
             "File should not exist after deletion"
         );
     }
+
+    #[tokio::test]
+    async fn test_path_confinement_rejects_escape_and_absolute_paths() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        assert!(
+            functions
+                .read_file(ReadFileArgs {
+                    path: "../../etc/passwd".to_string(),
+                })
+                .await
+                .is_err(),
+            "Paths that escape the repo root should be rejected"
+        );
+
+        assert!(
+            functions
+                .read_file(ReadFileArgs {
+                    path: "/etc/passwd".to_string(),
+                })
+                .await
+                .is_err(),
+            "Absolute paths should be rejected"
+        );
+
+        assert!(
+            functions
+                .write_file(WriteFileArgs {
+                    path: "src/../../outside.txt".to_string(),
+                    content: "evil".to_string(),
+                })
+                .await
+                .is_err(),
+            "Paths that pop above the repo root via a nested `..` should be rejected"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_exact_match() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\nLine2\nLine3\nLine4").expect("Should write to test file");
+
+        let patch = "@@ -2,2 +2,2 @@\n Line2\n-Line3\n+Line3 Changed\n Line4\n";
+        let result = functions
+            .apply_patch(ApplyPatchArgs {
+                path: "test.txt".to_string(),
+                patch: patch.to_string(),
+            })
+            .await
+            .expect("Patch should apply");
+
+        assert_eq!(result.new_contents, "Line1\nLine2\nLine3 Changed\nLine4");
+        let file_content = fs::read_to_string(file_path).expect("Should read patched file");
+        assert_eq!(file_content, "Line1\nLine2\nLine3 Changed\nLine4");
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_preserves_crlf_and_trailing_newline() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\r\nLine2\r\nLine3\r\nLine4\r\n")
+            .expect("Should write to test file");
+
+        let patch = "@@ -2,2 +2,2 @@\n Line2\n-Line3\n+Line3 Changed\n Line4\n";
+        let result = functions
+            .apply_patch(ApplyPatchArgs {
+                path: "test.txt".to_string(),
+                patch: patch.to_string(),
+            })
+            .await
+            .expect("Patch should apply");
+
+        assert_eq!(
+            result.new_contents,
+            "Line1\r\nLine2\r\nLine3 Changed\r\nLine4\r\n"
+        );
+        let file_content = fs::read_to_string(file_path).expect("Should read patched file");
+        assert_eq!(
+            file_content, "Line1\r\nLine2\r\nLine3 Changed\r\nLine4\r\n",
+            "CRLF line endings and the trailing newline should both be preserved"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_tolerates_drifted_line_numbers() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        // The real "Line3" is at line 4, but the hunk claims line 3 - the
+        // window scan should still find and patch the right line.
+        fs::write(&file_path, "X\nY\nLine2\nLine3\nLine4\nZ").expect("Should write to test file");
+
+        let patch = "@@ -3,2 +3,2 @@\n Line3\n-Line4\n+Line4 Changed\n";
+        let result = functions
+            .apply_patch(ApplyPatchArgs {
+                path: "test.txt".to_string(),
+                patch: patch.to_string(),
+            })
+            .await
+            .expect("Patch should apply despite drifted line numbers");
+
+        assert_eq!(result.new_contents, "X\nY\nLine2\nLine3\nLine4 Changed\nZ");
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_conflict_is_rejected() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let file_path = repo_path.join("test.txt");
+        fs::write(&file_path, "Line1\nLine2\nLine3").expect("Should write to test file");
+
+        let patch = "@@ -1,1 +1,1 @@\n-Line that does not exist\n+Replacement\n";
+        let err = functions
+            .apply_patch(ApplyPatchArgs {
+                path: "test.txt".to_string(),
+                patch: patch.to_string(),
+            })
+            .await
+            .expect_err("Patch with no matching context should be rejected");
+        assert!(err.to_string().contains("Conflict"));
+
+        let file_content = fs::read_to_string(file_path).expect("Should read untouched file");
+        assert_eq!(
+            file_content, "Line1\nLine2\nLine3",
+            "A rejected patch must not partially modify the file"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stage_and_commit_creates_initial_commit() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        fs::write(repo_path.join("test.txt"), "Line1").expect("Should write to test file");
+
+        functions
+            .stage_files(StageFilesArgs {
+                paths: vec!["test.txt".to_string()],
+            })
+            .await
+            .expect("Staging should succeed");
+
+        let oid = functions
+            .commit(CommitArgs {
+                message: "Initial commit".to_string(),
+            })
+            .await
+            .expect("Commit should succeed");
+
+        let commit = repo
+            .find_commit(git2::Oid::from_str(&oid).unwrap())
+            .expect("Commit should exist in the repository");
+        assert_eq!(commit.message(), Some("Initial commit"));
+        assert_eq!(commit.parent_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unstage_files_resets_to_head() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        fs::write(repo_path.join("test.txt"), "Line1").expect("Should write to test file");
+        functions
+            .stage_files(StageFilesArgs {
+                paths: vec!["test.txt".to_string()],
+            })
+            .await
+            .expect("Staging should succeed");
+        functions
+            .commit(CommitArgs {
+                message: "Initial commit".to_string(),
+            })
+            .await
+            .expect("Commit should succeed");
+
+        fs::write(repo_path.join("test.txt"), "Line1\nLine2").expect("Should rewrite test file");
+        functions
+            .stage_files(StageFilesArgs {
+                paths: vec!["test.txt".to_string()],
+            })
+            .await
+            .expect("Re-staging should succeed");
+        functions
+            .unstage_files(StageFilesArgs {
+                paths: vec!["test.txt".to_string()],
+            })
+            .await
+            .expect("Unstaging should succeed");
+
+        let head_contents = functions
+            .read_head_file(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(head_contents, Some("Line1".to_string()));
+
+        // The index entry must be back to HEAD's blob, not the re-staged one.
+        let repo = Repository::open(&repo_path).expect("Should reopen the repository");
+        let index_entry = repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("test.txt"), 0)
+            .expect("test.txt should still be in the index");
+        let head_entry = repo
+            .head()
+            .unwrap()
+            .peel_to_tree()
+            .unwrap()
+            .get_path(Path::new("test.txt"))
+            .unwrap();
+        assert_eq!(index_entry.id, head_entry.id());
+
+        let working_contents = functions
+            .read_file(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            working_contents,
+            Some("Line1\nLine2".to_string()),
+            "Unstaging must not touch the working tree"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diff_file_against_head() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        fs::write(repo_path.join("test.txt"), "Line1\nLine2\nLine3")
+            .expect("Should write to test file");
+        functions
+            .stage_files(StageFilesArgs {
+                paths: vec!["test.txt".to_string()],
+            })
+            .await
+            .expect("Staging should succeed");
+        functions
+            .commit(CommitArgs {
+                message: "Initial commit".to_string(),
+            })
+            .await
+            .expect("Commit should succeed");
+
+        fs::write(
+            repo_path.join("test.txt"),
+            "Line1\nLine2 Changed\nLine3\nLine4",
+        )
+        .expect("Should rewrite test file");
+
+        let result = functions
+            .diff_file(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .expect("Diffing should succeed");
+
+        assert_eq!(
+            result.modifications,
+            vec![
+                LineModification {
+                    line: 2,
+                    content: "Line2".to_string(),
+                    is_deletion: true,
+                },
+                LineModification {
+                    line: 2,
+                    content: "Line2 Changed".to_string(),
+                    is_deletion: false,
+                },
+                LineModification {
+                    line: 4,
+                    content: "Line4".to_string(),
+                    is_deletion: false,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diff_file_new_untracked_file() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        fs::write(repo_path.join("test.txt"), "Line1\nLine2").expect("Should write to test file");
+
+        let result = functions
+            .diff_file(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .expect("Diffing an untracked file should succeed");
+
+        assert_eq!(
+            result.modifications,
+            vec![
+                LineModification {
+                    line: 1,
+                    content: "Line1".to_string(),
+                    is_deletion: false,
+                },
+                LineModification {
+                    line: 2,
+                    content: "Line2".to_string(),
+                    is_deletion: false,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_modify_file_records_restorable_snapshots() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        fs::write(repo_path.join("test.txt"), "Line1").expect("Should write to test file");
+
+        functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::Insert {
+                    start_line: 1,
+                    content: "Line2".to_string(),
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("First modification should succeed");
+        functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::Insert {
+                    start_line: 1,
+                    content: "Line3".to_string(),
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("Second modification should succeed");
+
+        let versions = functions
+            .list_versions(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .expect("Listing versions should succeed");
+        assert_eq!(
+            versions,
+            vec![VersionInfo { version: 1 }, VersionInfo { version: 2 }]
+        );
+
+        functions
+            .restore_version(RestoreVersionArgs {
+                path: "test.txt".to_string(),
+                version: 1,
+            })
+            .await
+            .expect("Restoring a snapshot should succeed");
+
+        let content = functions
+            .read_file(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(content, Some("Line1".to_string()));
+
+        // Restoring itself snapshots the pre-restore contents, so the undo
+        // is itself undoable.
+        let versions = functions
+            .list_versions(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .expect("Listing versions should succeed");
+        assert_eq!(versions.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_are_hidden_from_list_files() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        fs::write(repo_path.join("test.txt"), "Line1").expect("Should write to test file");
+        functions
+            .write_file(WriteFileArgs {
+                path: "test.txt".to_string(),
+                content: "Line1 Changed".to_string(),
+            })
+            .await
+            .expect("Overwrite should succeed");
+
+        let files = functions
+            .list_files()
+            .await
+            .expect("Listing files should succeed");
+        assert!(
+            files.iter().all(|f| !f.contains("jdev")),
+            "snapshots must not appear in list_files, got: {:?}",
+            files
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ignore_path_creates_and_appends_gitignore() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let entries = functions
+            .ignore_path(IgnorePathArgs {
+                path: "build/".to_string(),
+            })
+            .await
+            .expect("Ignoring a path should succeed");
+        assert_eq!(entries, vec!["build/".to_string()]);
+
+        // Pre-existing .gitignore content with no trailing newline must not
+        // get concatenated onto the new entry.
+        fs::write(repo_path.join(".gitignore"), "build/\n*.log").unwrap();
+        let entries = functions
+            .ignore_path(IgnorePathArgs {
+                path: "scratch.txt".to_string(),
+            })
+            .await
+            .expect("Ignoring a second path should succeed");
+        assert_eq!(
+            entries,
+            vec![
+                "build/".to_string(),
+                "*.log".to_string(),
+                "scratch.txt".to_string()
+            ]
+        );
+
+        // Ignoring an already-ignored path is a no-op.
+        let entries = functions
+            .ignore_path(IgnorePathArgs {
+                path: "build/".to_string(),
+            })
+            .await
+            .expect("Re-ignoring an existing path should succeed");
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ignore_path_refuses_to_ignore_gitignore_itself() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        let err = functions
+            .ignore_path(IgnorePathArgs {
+                path: ".gitignore".to_string(),
+            })
+            .await
+            .expect_err("Ignoring .gitignore itself should be rejected");
+        assert!(err.to_string().contains(".gitignore"));
+    }
+
+    #[tokio::test]
+    async fn test_move_file_rejects_existing_destination_without_overwrite() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        fs::write(repo_path.join("a.txt"), "A").unwrap();
+        fs::write(repo_path.join("b.txt"), "B").unwrap();
+
+        functions
+            .move_file(MoveFileArgs {
+                source_path: "a.txt".to_string(),
+                destination_path: "b.txt".to_string(),
+                overwrite: false,
+            })
+            .await
+            .expect_err("Move onto an existing file without overwrite should be rejected");
+
+        functions
+            .move_file(MoveFileArgs {
+                source_path: "a.txt".to_string(),
+                destination_path: "b.txt".to_string(),
+                overwrite: true,
+            })
+            .await
+            .expect("Move onto an existing file with overwrite should succeed");
+
+        assert_eq!(fs::read_to_string(repo_path.join("b.txt")).unwrap(), "A");
+        assert!(!repo_path.join("a.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_copy_file_leaves_source_in_place() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::new(repo_path.clone()).expect("Functions::new should work");
+
+        fs::write(repo_path.join("a.txt"), "A").unwrap();
+
+        functions
+            .copy_file(CopyFileArgs {
+                source_path: "a.txt".to_string(),
+                destination_path: "b.txt".to_string(),
+                overwrite: false,
+            })
+            .await
+            .expect("Copy should succeed");
+
+        assert_eq!(fs::read_to_string(repo_path.join("a.txt")).unwrap(), "A");
+        assert_eq!(fs::read_to_string(repo_path.join("b.txt")).unwrap(), "A");
+
+        functions
+            .copy_file(CopyFileArgs {
+                source_path: "a.txt".to_string(),
+                destination_path: "b.txt".to_string(),
+                overwrite: false,
+            })
+            .await
+            .expect_err("Copy onto an existing file without overwrite should be rejected");
+    }
+
+    // The tests below exercise the same scenarios `with_fs`'s doc comment
+    // calls out, but against `FakeFs` instead of disk - `Repository::init`
+    // still needs a real directory to set up git metadata in, but no file
+    // content ever touches it; every read/write goes through the in-memory
+    // `FakeFs`.
+
+    #[tokio::test]
+    async fn test_fake_fs_path_confinement_rejects_escape_and_absolute_paths() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::with_fs(repo_path, Box::new(FakeFs::new()))
+            .expect("Functions::with_fs should work");
+
+        assert!(
+            functions
+                .read_file(ReadFileArgs {
+                    path: "../../etc/passwd".to_string(),
+                })
+                .await
+                .is_err(),
+            "Paths that escape the repo root should be rejected"
+        );
+
+        assert!(
+            functions
+                .write_file(WriteFileArgs {
+                    path: "/etc/passwd".to_string(),
+                    content: "evil".to_string(),
+                })
+                .await
+                .is_err(),
+            "Absolute paths should be rejected"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_fs_modify_file_and_apply_patch() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::with_fs(repo_path, Box::new(FakeFs::new()))
+            .expect("Functions::with_fs should work");
+
+        functions
+            .write_file(WriteFileArgs {
+                path: "test.txt".to_string(),
+                content: "Line1\nLine2\nLine3".to_string(),
+            })
+            .await
+            .expect("Initial write should succeed");
+
+        functions
+            .modify_file(ModifyFileArgs {
+                path: "test.txt".to_string(),
+                modification: FileModification::Replace {
+                    start_line: 2,
+                    end_line: 3,
+                    content: "Replaced Line".to_string(),
+                },
+                expected_hash: None,
+            })
+            .await
+            .expect("modify_file should succeed against FakeFs");
+
+        let patch = "@@ -1,2 +1,2 @@\n Line1\n-Replaced Line\n+Patched Line\n";
+        let result = functions
+            .apply_patch(ApplyPatchArgs {
+                path: "test.txt".to_string(),
+                patch: patch.to_string(),
+            })
+            .await
+            .expect("apply_patch should succeed against FakeFs");
+        assert_eq!(result.new_contents, "Line1\nPatched Line\nLine3");
+
+        let file_content = functions
+            .read_file(ReadFileArgs {
+                path: "test.txt".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(file_content, Some("Line1\nPatched Line\nLine3".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_fake_fs_move_file_rejects_existing_destination_without_overwrite() {
+        let temp_dir = tempdir().expect("Failed to create a temporary directory");
+        let repo_path = temp_dir.into_path();
+        let _repo = Repository::init(&repo_path).expect("Failed to initialize a repository");
+        let functions = Functions::with_fs(repo_path, Box::new(FakeFs::new()))
+            .expect("Functions::with_fs should work");
+
+        functions
+            .write_file(WriteFileArgs {
+                path: "a.txt".to_string(),
+                content: "A".to_string(),
+            })
+            .await
+            .expect("Should write a.txt");
+        functions
+            .write_file(WriteFileArgs {
+                path: "b.txt".to_string(),
+                content: "B".to_string(),
+            })
+            .await
+            .expect("Should write b.txt");
+
+        functions
+            .move_file(MoveFileArgs {
+                source_path: "a.txt".to_string(),
+                destination_path: "b.txt".to_string(),
+                overwrite: false,
+            })
+            .await
+            .expect_err("Move onto an existing file without overwrite should be rejected");
+
+        functions
+            .move_file(MoveFileArgs {
+                source_path: "a.txt".to_string(),
+                destination_path: "b.txt".to_string(),
+                overwrite: true,
+            })
+            .await
+            .expect("Move onto an existing file with overwrite should succeed");
+
+        let b_content = functions
+            .read_file(ReadFileArgs {
+                path: "b.txt".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(b_content, Some("A".to_string()));
+        assert!(!functions
+            .exists(ReadFileArgs {
+                path: "a.txt".to_string(),
+            })
+            .await
+            .unwrap());
+    }
 }
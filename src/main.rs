@@ -2,6 +2,7 @@ use clap::Parser;
 use functions::Functions;
 use socket::connect;
 
+mod fs;
 mod functions;
 mod print;
 mod socket;
@@ -1,5 +1,8 @@
 use crate::{
-    functions::{WriteFileArgs, DeleteFileArgs, ReadFileArgs},
+    functions::{
+        ApplyPatchArgs, CommitArgs, DeleteFileArgs, DiffFileResult, FileChangeEvent,
+        IgnorePathArgs, ReadFileArgs, RestoreVersionArgs, StageFilesArgs, WriteFileArgs,
+    },
     socket::{FunctionCall, FunctionResult, FunctionReturnData},
 };
 use colored::Colorize;
@@ -21,16 +24,16 @@ pub fn print_function_execution(exec: FunctionExecution) {
 
     let result = match exec.result {
         Status::Success(x) => x,
-        Status::Error(err) => {
-            println!("{}", "ERROR".red().bold());
+        Status::Error { kind, message } => {
+            println!("{} {}", "ERROR".red().bold(), format!("({:?})", kind).red());
             dbg!(exec.call);
-            dbg!(err);
+            dbg!(message);
             return;
         }
     };
 
     match (exec.call, result) {
-        (Fn::ListFiles {}, Data::ListFiles(files)) => {
+        (Fn::GetAllFiles {}, Data::GetAllFiles(files)) => {
             println!(
                 "{}{}{}",
                 "Listing all (".white().bold(),
@@ -61,6 +64,96 @@ pub fn print_function_execution(exec: FunctionExecution) {
                 "lines)".white().bold()
             )
         }
+        (Fn::Exists(ReadFileArgs { path }), Data::Exists(exists)) => {
+            println!(
+                "{} {} {}",
+                path.cyan().bold(),
+                "exists:".white().bold(),
+                exists.to_string().yellow().bold()
+            )
+        }
+        (Fn::HashFile(ReadFileArgs { path }), Data::HashFile(hash)) => {
+            println!(
+                "{} {} {}",
+                "Hashed".white().bold(),
+                path.cyan().bold(),
+                hash.unwrap_or_else(|| "(missing)".to_string()).yellow()
+            )
+        }
+        (Fn::DiffFile(ReadFileArgs { path }), Data::DiffFile(DiffFileResult { modifications })) => {
+            println!(
+                "{} {} {}",
+                "Diffing".white().bold(),
+                path.cyan().bold(),
+                "against HEAD".white().bold()
+            );
+            for modification in modifications {
+                print_line_content(
+                    modification.line,
+                    &modification.content,
+                    modification.is_deletion,
+                );
+            }
+        }
+        (Fn::ApplyPatch(ApplyPatchArgs { path, .. }), Data::ApplyPatch(result)) => {
+            println!(
+                "{} {}",
+                "Applied patch to".white().bold(),
+                path.cyan().bold()
+            );
+            print_diff(&result.old_contents, &result.new_contents);
+        }
+        (Fn::StageFiles(StageFilesArgs { paths }), _) => {
+            println!(
+                "{} {}",
+                "Staged".white().bold(),
+                paths.join(", ").cyan().bold()
+            );
+        }
+        (Fn::UnstageFiles(StageFilesArgs { paths }), _) => {
+            println!(
+                "{} {}",
+                "Unstaged".white().bold(),
+                paths.join(", ").cyan().bold()
+            );
+        }
+        (Fn::Commit(CommitArgs { message }), Data::Commit(oid)) => {
+            println!(
+                "{} {} {} {}",
+                "Committed".white().bold(),
+                oid.cyan().bold(),
+                "-".white().bold(),
+                message
+            );
+        }
+        (Fn::ListVersions(ReadFileArgs { path }), Data::ListVersions(versions)) => {
+            println!(
+                "{} {} {} {}",
+                path.cyan().bold(),
+                "has".white().bold(),
+                versions.len().to_string().yellow().bold(),
+                "snapshot(s)".white().bold()
+            );
+        }
+        (Fn::RestoreVersion(RestoreVersionArgs { path, version }), _) => {
+            println!(
+                "{} {} {} {}",
+                "Restored".white().bold(),
+                path.cyan().bold(),
+                "to version".white().bold(),
+                version.to_string().yellow().bold()
+            );
+        }
+        (Fn::IgnorePath(IgnorePathArgs { path }), Data::IgnorePath(entries)) => {
+            println!(
+                "{} {} {} {} {}",
+                "Ignored".white().bold(),
+                path.cyan().bold(),
+                "(".white().bold(),
+                entries.len().to_string().yellow().bold(),
+                "entries in .gitignore)".white().bold()
+            );
+        }
         (Fn::DeleteFile(DeleteFileArgs { path }), _) => {
             println!(
                 "{} {}",
@@ -77,16 +170,44 @@ pub fn print_function_execution(exec: FunctionExecution) {
                 args.destination_path.cyan().bold()
             );
         }
+        (Fn::CopyFile(args), _) => {
+            println!(
+                "{} {} {} {}",
+                "Copied".white().bold(),
+                args.source_path.cyan().bold(),
+                "to".white().bold(),
+                args.destination_path.cyan().bold()
+            );
+        }
         (Fn::PrintMessage { message }, _) => {
             println!("{}", "Received message".white().bold());
             println!("{}", message);
         }
+        (Fn::Watch(args), _) => {
+            println!(
+                "{} {}",
+                "Watching for changes under".white().bold(),
+                args.paths.join(", ").cyan().bold()
+            );
+        }
         v => panic!("unrecognised function pattern {:?}", v),
     }
 
     println!(); // Just to space things out a little.
 }
 
+/// Renders a `FileChanged` event pushed by a `Watch` subscription. These
+/// arrive outside the normal request/response flow, so they're printed
+/// directly rather than through `print_function_execution`.
+pub fn print_file_changed(event: &FileChangeEvent) {
+    println!(
+        "{} {} {}",
+        format!("{:?}", event.kind).yellow().bold(),
+        "-".white().bold(),
+        event.path.cyan().bold()
+    );
+}
+
 fn print_diff(old: &str, new: &str) {
     let diff = TextDiff::from_lines(old, new);
     for change in diff
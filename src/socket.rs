@@ -1,12 +1,16 @@
 use crate::{
     functions::{
-        WriteFileArgs, DeleteFileArgs, Functions, ModifyFileArgs, ModifyFileResult, MoveFileArgs,
-        ReadFileArgs,
+        ApplyPatchArgs, CommitArgs, CopyFileArgs, DeleteFileArgs, DiffFileResult, FileChangeEvent,
+        Functions, IgnorePathArgs, ModifyFileArgs, ModifyFileOutcome, ModifyFileResult,
+        MoveFileArgs, ReadFileArgs, RestoreVersionArgs, StageFilesArgs, VersionInfo, WatchArgs,
+        WriteFileArgs,
     },
-    print::{print_function_execution, FunctionExecution},
+    print::{print_file_changed, print_function_execution, FunctionExecution},
 };
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::io;
+use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 
 #[derive(Deserialize, Debug, Clone)]
@@ -18,7 +22,34 @@ pub enum FunctionCall {
     DeleteFile(DeleteFileArgs),
     ModifyFile(ModifyFileArgs),
     MoveFile(MoveFileArgs),
-    PrintMessage { message: String },
+    CopyFile(CopyFileArgs),
+    Exists(ReadFileArgs),
+    DiffFile(ReadFileArgs),
+    ApplyPatch(ApplyPatchArgs),
+    /// Returns the SHA-256 hex digest of a file's contents, to be threaded
+    /// back in as `ModifyFileArgs::expected_hash` for safe read-modify-write.
+    HashFile(ReadFileArgs),
+    /// Stages `paths` into the index, mirroring `git add`.
+    StageFiles(StageFilesArgs),
+    /// Unstages `paths`, mirroring `git reset`.
+    UnstageFiles(StageFilesArgs),
+    /// Commits the current index on HEAD, checkpointing whatever has been
+    /// staged.
+    Commit(CommitArgs),
+    /// Lists the recorded snapshots for a file, oldest first.
+    ListVersions(ReadFileArgs),
+    /// Rewrites a file's working-tree contents from a recorded snapshot.
+    RestoreVersion(RestoreVersionArgs),
+    /// Appends a path to the repo root `.gitignore`.
+    IgnorePath(IgnorePathArgs),
+    /// Registers filesystem watchers on `paths`. Unlike the other variants,
+    /// this doesn't resolve to a single response: an immediate `Null` ack is
+    /// sent back, and `FileChanged` events stream in afterwards as they
+    /// occur, for as long as the connection stays open.
+    Watch(WatchArgs),
+    PrintMessage {
+        message: String,
+    },
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -27,22 +58,63 @@ pub enum FunctionReturnData {
     Null(()),
     GetAllFiles(Vec<String>),
     WriteFile(Option<String>),
-    ModifyFile(ModifyFileResult),
+    ModifyFile(ModifyFileOutcome),
     ReadFile(Option<String>),
+    Exists(bool),
+    DiffFile(DiffFileResult),
+    ApplyPatch(ModifyFileResult),
+    HashFile(Option<String>),
+    Commit(String),
+    ListVersions(Vec<VersionInfo>),
+    IgnorePath(Vec<String>),
+    FileChanged(FileChangeEvent),
+}
+
+/// A coarse, serde-tagged classification of a `FunctionResult::Error`, so
+/// the server can branch on the kind of failure (missing file, clobbered
+/// destination, ...) instead of pattern-matching the English error text we
+/// happen to produce.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    InvalidPath,
+    Io,
+    Other,
+}
+
+impl ErrorKind {
+    fn from_anyhow(err: &anyhow::Error) -> Self {
+        match err.downcast_ref::<io::Error>() {
+            Some(io_err) => match io_err.kind() {
+                io::ErrorKind::NotFound => ErrorKind::NotFound,
+                io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+                io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+                io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => ErrorKind::InvalidPath,
+                _ => ErrorKind::Io,
+            },
+            None => ErrorKind::Other,
+        }
+    }
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(tag = "status", content = "data", rename_all = "snake_case")]
 pub enum FunctionResult {
     Success(FunctionReturnData),
-    Error(String),
+    Error { kind: ErrorKind, message: String },
 }
 
 macro_rules! call {
     ($func_call:expr, $variant:ident) => {{
         $func_call
             .map(|x| FunctionResult::Success(FunctionReturnData::$variant(x)))
-            .unwrap_or_else(|e| FunctionResult::Error(e.to_string()))
+            .unwrap_or_else(|e| FunctionResult::Error {
+                kind: ErrorKind::from_anyhow(&e),
+                message: e.to_string(),
+            })
     }};
 }
 
@@ -58,43 +130,104 @@ pub async fn connect(
 
     write.send(Message::Text(query)).await?;
 
-    // Read messages from the server
-    while let Some(message) = read.next().await {
-        match message {
-            Ok(Message::Text(text)) => {
-                let call = match serde_json::from_str::<FunctionCall>(&text) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        eprintln!("Err: {}", e);
-                        eprintln!("Text: {}", text);
-                        continue;
-                    }
-                };
-                let result = match call.clone() {
-                    FunctionCall::GetAllFiles {} => call!(functions.get_all_files(), GetAllFiles),
-                    FunctionCall::ReadFile(args) => call!(functions.read_file(args), ReadFile),
-                    FunctionCall::WriteFile(args) => call!(functions.write_file(args), WriteFile),
-                    FunctionCall::DeleteFile(args) => call!(functions.delete_file(args), Null),
-                    FunctionCall::ModifyFile(args) => {
-                        call!(functions.modify_file(args), ModifyFile)
-                    }
-                    FunctionCall::MoveFile(args) => call!(functions.move_file(args), Null),
-                    FunctionCall::PrintMessage { message } => {
-                        println!("{}", message);
-                        return Ok(());
+    // Carries `FileChanged` events from background `Functions::watch` threads
+    // into this loop, so they can be pushed out over the same websocket
+    // connection alongside ordinary request/response results.
+    let (push_tx, mut push_rx) = mpsc::unbounded_channel::<FileChangeEvent>();
+
+    // Read messages from the server, interleaved with any outstanding
+    // `Watch` subscriptions pushing `FileChanged` events.
+    loop {
+        tokio::select! {
+            message = read.next() => {
+                let Some(message) = message else { break };
+                match message {
+                    Ok(Message::Text(text)) => {
+                        let call = match serde_json::from_str::<FunctionCall>(&text) {
+                            Ok(v) => v,
+                            Err(e) => {
+                                eprintln!("Err: {}", e);
+                                eprintln!("Text: {}", text);
+                                continue;
+                            }
+                        };
+                        let result = match call.clone() {
+                            FunctionCall::GetAllFiles {} => {
+                                call!(functions.list_files().await, GetAllFiles)
+                            }
+                            FunctionCall::ReadFile(args) => {
+                                call!(functions.read_file(args).await, ReadFile)
+                            }
+                            FunctionCall::WriteFile(args) => {
+                                call!(functions.write_file(args).await, WriteFile)
+                            }
+                            FunctionCall::DeleteFile(args) => {
+                                call!(functions.delete_file(args).await, Null)
+                            }
+                            FunctionCall::ModifyFile(args) => {
+                                call!(functions.modify_file(args).await, ModifyFile)
+                            }
+                            FunctionCall::MoveFile(args) => call!(functions.move_file(args).await, Null),
+                            FunctionCall::CopyFile(args) => {
+                                call!(functions.copy_file(args).await, Null)
+                            }
+                            FunctionCall::Exists(args) => call!(functions.exists(args).await, Exists),
+                            FunctionCall::DiffFile(args) => {
+                                call!(functions.diff_file(args).await, DiffFile)
+                            }
+                            FunctionCall::ApplyPatch(args) => {
+                                call!(functions.apply_patch(args).await, ApplyPatch)
+                            }
+                            FunctionCall::HashFile(args) => {
+                                call!(functions.hash_file(args).await, HashFile)
+                            }
+                            FunctionCall::StageFiles(args) => {
+                                call!(functions.stage_files(args).await, Null)
+                            }
+                            FunctionCall::UnstageFiles(args) => {
+                                call!(functions.unstage_files(args).await, Null)
+                            }
+                            FunctionCall::Commit(args) => {
+                                call!(functions.commit(args).await, Commit)
+                            }
+                            FunctionCall::ListVersions(args) => {
+                                call!(functions.list_versions(args).await, ListVersions)
+                            }
+                            FunctionCall::RestoreVersion(args) => {
+                                call!(functions.restore_version(args).await, Null)
+                            }
+                            FunctionCall::IgnorePath(args) => {
+                                call!(functions.ignore_path(args).await, IgnorePath)
+                            }
+                            FunctionCall::Watch(args) => {
+                                call!(functions.watch(args, push_tx.clone()), Null)
+                            }
+                            FunctionCall::PrintMessage { message } => {
+                                println!("{}", message);
+                                return Ok(());
+                            }
+                        };
+                        print_function_execution(FunctionExecution {
+                            call,
+                            result: result.clone(),
+                        });
+                        let result_str = Message::Text(serde_json::to_string(&result).unwrap());
+                        if let Err(err) = write.send(result_str).await {
+                            eprintln!("Error sending outgoing message: {}", &err)
+                        }
                     }
-                };
-                print_function_execution(FunctionExecution {
-                    call,
-                    result: result.clone(),
-                });
+                    Ok(_) => println!("Received non-text message"),
+                    Err(e) => eprintln!("Error handling incoming message: {}", &e),
+                }
+            }
+            Some(event) = push_rx.recv() => {
+                print_file_changed(&event);
+                let result = FunctionResult::Success(FunctionReturnData::FileChanged(event));
                 let result_str = Message::Text(serde_json::to_string(&result).unwrap());
                 if let Err(err) = write.send(result_str).await {
                     eprintln!("Error sending outgoing message: {}", &err)
                 }
             }
-            Ok(_) => println!("Received non-text message"),
-            Err(e) => eprintln!("Error handling incoming message: {}", &e),
         }
     }
 